@@ -105,7 +105,11 @@ mod inner {
 mod inner {
     use std::env;
     use std::ffi::OsString;
+    use std::fs::OpenOptions;
+    use std::io::{self, Write};
+    use std::path::PathBuf;
     use std::process::{Command, Stdio, exit};
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     use common::ui::UI;
     use hcore::crypto::default_cache_key_path;
@@ -119,6 +123,225 @@ mod inner {
     const DOCKER_CMD_ENVVAR: &'static str = "HAB_DOCKER_BINARY";
     const DOCKER_IMAGE: &'static str = "habitat-docker-registry.bintray.io/studio";
     const DOCKER_IMAGE_ENVVAR: &'static str = "HAB_DOCKER_STUDIO_IMAGE";
+    // Repo prefixes (registry host + path, no tag) that a resolved image reference must start
+    // with to be considered trusted. Additional prefixes may be appended via
+    // `HAB_STUDIO_TRUSTED_IMAGE_PREFIXES`, a `:`-separated list.
+    const TRUSTED_IMAGE_PREFIXES_ENVVAR: &'static str = "HAB_STUDIO_TRUSTED_IMAGE_PREFIXES";
+    // When set (to any value), enables Docker Content Trust so the engine itself refuses to
+    // pull or run an unsigned image.
+    const VERIFY_IMAGE_ENVVAR: &'static str = "HAB_STUDIO_VERIFY_IMAGE";
+    const DOCKER_CONTENT_TRUST_ENVVAR: &'static str = "DOCKER_CONTENT_TRUST";
+    // Controls when `docker pull` runs before launching the Studio: `always` (default, today's
+    // behavior), `missing` (pull only if the image isn't already local), or `never` (never pull).
+    const PULL_POLICY_ENVVAR: &'static str = "HAB_DOCKER_PULL_POLICY";
+    // Overrides the `--platform` passed to `docker pull`/`docker run`. Defaults to the host's own
+    // architecture so cross-arch Studios (e.g. building arm64 on an amd64 host via QEMU binfmt
+    // handlers registered with the engine) are opt-in, not silent emulation.
+    const PLATFORM_ENVVAR: &'static str = "HAB_STUDIO_PLATFORM";
+    // Selects the Studio's security profile: a path to a custom seccomp JSON profile, the
+    // literal `unconfined` to disable seccomp filtering, or `privileged` to restore the old
+    // `--privileged` behavior. Unset uses the bundled default restrictive profile.
+    const SECCOMP_ENVVAR: &'static str = "HAB_STUDIO_SECCOMP";
+    // Capabilities retained under every profile except `privileged`, after `--cap-drop ALL`.
+    // The Studio forks and chroots heavily while building and installing packages, so it needs
+    // enough to do that without the blanket `--privileged` grant.
+    const STUDIO_CAPABILITIES: &'static [&'static str] =
+        &["SYS_ADMIN", "SYS_CHROOT", "SYS_PTRACE", "MKNOD", "AUDIT_WRITE", "SETFCAP"];
+    // Resource-limit pass-throughs. Unset by default, so a Studio runs unconstrained exactly as
+    // it always has; set any of these to cap what a build can do to the host.
+    const SHM_SIZE_ENVVAR: &'static str = "HAB_STUDIO_SHM_SIZE";
+    const MEMORY_ENVVAR: &'static str = "HAB_STUDIO_MEMORY";
+    const CPUS_ENVVAR: &'static str = "HAB_STUDIO_CPUS";
+    // A restrictive default seccomp profile: deny-by-default, with the syscall groups a
+    // container engine normally allows plus `clone`/`clone3` (the Studio forks heavily) and the
+    // mount-related syscalls its package builds need.
+    const DEFAULT_SECCOMP_PROFILE: &'static str = r#"{
+  "defaultAction": "SCMP_ACT_ERRNO",
+  "archMap": [
+    {"architecture": "SCMP_ARCH_X86_64", "subArchitectures": ["SCMP_ARCH_X86", "SCMP_ARCH_X32"]},
+    {"architecture": "SCMP_ARCH_AARCH64", "subArchitectures": ["SCMP_ARCH_ARM"]}
+  ],
+  "syscalls": [
+    {
+      "names": [
+        "clone", "clone3", "fork", "vfork", "execve", "execveat", "wait4", "waitid",
+        "read", "write", "open", "openat", "close", "stat", "fstat", "lstat", "poll",
+        "lseek", "mmap", "mprotect", "munmap", "brk", "rt_sigaction", "rt_sigprocmask",
+        "ioctl", "pread64", "pwrite64", "readv", "writev", "access", "pipe", "select",
+        "dup", "dup2", "socket", "connect", "accept", "sendto", "recvfrom", "bind",
+        "listen", "clock_gettime", "exit", "exit_group", "getpid", "getppid", "getuid",
+        "getgid", "setuid", "setgid", "capget", "capset", "prctl", "arch_prctl",
+        "mount", "umount2", "pivot_root", "chroot", "chdir", "mkdir", "rmdir", "unlink",
+        "rename", "symlink", "readlink", "chmod", "chown", "statfs", "fstatfs",
+        "futex", "sched_yield", "nanosleep", "getrandom", "epoll_create1", "epoll_ctl",
+        "epoll_wait", "eventfd2", "signalfd4", "inotify_init1", "inotify_add_watch",
+        "fcntl", "getdents64", "set_tid_address", "set_robust_list", "rseq"
+      ],
+      "action": "SCMP_ACT_ALLOW"
+    }
+  ]
+}"#;
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum PullPolicy {
+        Always,
+        Missing,
+        Never,
+    }
+
+    impl PullPolicy {
+        fn from_env() -> Self {
+            match henv::var(PULL_POLICY_ENVVAR) {
+                Ok(ref policy) if policy == "missing" => PullPolicy::Missing,
+                Ok(ref policy) if policy == "never" => PullPolicy::Never,
+                _ => PullPolicy::Always,
+            }
+        }
+    }
+
+    enum SecurityProfile {
+        /// Restore the old `--privileged` behavior; no seccomp filtering or capability drop.
+        Privileged,
+        /// `--security-opt seccomp=unconfined`, but capabilities are still dropped to the
+        /// minimal set the Studio needs.
+        Unconfined,
+        /// A user-supplied seccomp profile at this path.
+        Custom(PathBuf),
+        /// The bundled default restrictive profile.
+        Default,
+    }
+
+    impl SecurityProfile {
+        fn from_env() -> Self {
+            match henv::var(SECCOMP_ENVVAR) {
+                Ok(ref v) if v == "privileged" => SecurityProfile::Privileged,
+                Ok(ref v) if v == "unconfined" => SecurityProfile::Unconfined,
+                Ok(v) => SecurityProfile::Custom(PathBuf::from(v)),
+                Err(_) => SecurityProfile::Default,
+            }
+        }
+    }
+
+    /// Writes the bundled default seccomp profile to a fresh temp file named from the current
+    /// system time and
+    /// returns its path, so it can be handed to `--security-opt seccomp=<path>`.
+    ///
+    /// `/tmp` is world-writable, so the file is created with `O_CREAT | O_EXCL` (via
+    /// `create_new`): this refuses to follow a pre-planted symlink and fails outright if
+    /// anything, including a symlink, already occupies the path, instead of silently
+    /// overwriting whatever it points to.
+    fn write_default_seccomp_profile() -> Result<PathBuf> {
+        for _ in 0..8 {
+            let nonce = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| (d.as_secs() << 32) ^ (d.subsec_nanos() as u64))
+                .unwrap_or(0);
+            let path = env::temp_dir().join(format!("hab-studio-seccomp-{:x}.json", nonce));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    try!(file.write_all(DEFAULT_SECCOMP_PROFILE.as_bytes()));
+                    return Ok(path);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
+        Err(Error::SeccompProfileWriteFailed)
+    }
+
+    /// Applies the resolved security profile to the `docker run` command: either `--privileged`,
+    /// or a `--security-opt seccomp=...` plus a minimal `--cap-drop ALL --cap-add ...` set.
+    fn apply_security_profile(command: &mut Command) -> Result<()> {
+        match SecurityProfile::from_env() {
+            SecurityProfile::Privileged => {
+                command.arg("--privileged");
+            }
+            profile => {
+                let seccomp = match profile {
+                    SecurityProfile::Unconfined => "unconfined".to_string(),
+                    SecurityProfile::Custom(path) => path.to_string_lossy().into_owned(),
+                    SecurityProfile::Default => {
+                        try!(write_default_seccomp_profile()).to_string_lossy().into_owned()
+                    }
+                    SecurityProfile::Privileged => unreachable!(),
+                };
+                command.arg("--security-opt").arg(format!("seccomp={}", seccomp));
+                command.arg("--cap-drop").arg("ALL");
+                for cap in STUDIO_CAPABILITIES {
+                    command.arg("--cap-add").arg(cap);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates a Docker size value (`--shm-size`/`--memory`): a numeric amount followed by an
+    /// optional `b`/`k`/`m`/`g` (or `kb`/`mb`/`gb`) unit, e.g. `512m` or `2g`.
+    fn validate_size(value: &str) -> Result<()> {
+        let split_at = value.find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(value.len());
+        let (amount, unit) = value.split_at(split_at);
+        let valid_unit = match unit.to_lowercase().as_str() {
+            "" | "b" | "k" | "kb" | "m" | "mb" | "g" | "gb" => true,
+            _ => false,
+        };
+        if amount.is_empty() || amount.parse::<f64>().is_err() || !valid_unit {
+            Err(Error::InvalidStudioResourceLimit(value.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Validates a Docker CPU value: either a plain number of CPUs for `--cpus` (e.g. `1.5`) or a
+    /// CPU set for `--cpuset-cpus` (e.g. `0-3` or `0,2`). Both forms must be non-negative: a
+    /// leading `-` parses as a negative CPU count under the first branch, and under the second
+    /// branch it's just a `-` character like any other, so without this check `-1` passed both
+    /// and was handed to Docker as `--cpuset-cpus -1`, which isn't a valid cpuset.
+    fn validate_cpus(value: &str) -> Result<()> {
+        let is_valid = value.parse::<f64>().map(|n| n >= 0.0).unwrap_or(false) ||
+                       (!value.is_empty() &&
+                        value.chars().next().map_or(false, |c| c.is_ascii_digit()) &&
+                        value.chars().all(|c| c.is_ascii_digit() || c == ',' || c == '-'));
+        if is_valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidStudioResourceLimit(value.to_string()))
+        }
+    }
+
+    /// A CPU value is a cpuset (passed via `--cpuset-cpus`) rather than a plain CPU count
+    /// (`--cpus`) if it lists or ranges specific CPUs. Requiring it to start with a digit keeps
+    /// a bare negative number like `-1` out of this branch rather than treating its `-` as a
+    /// range separator.
+    fn is_cpuset(value: &str) -> bool {
+        value.chars().next().map_or(false, |c| c.is_ascii_digit()) &&
+        (value.contains(',') || value.contains('-'))
+    }
+
+    /// Applies the optional `HAB_STUDIO_SHM_SIZE` / `HAB_STUDIO_MEMORY` / `HAB_STUDIO_CPUS`
+    /// resource limits to the `docker run` command, validating each before it's appended so a
+    /// typo surfaces as a clear error instead of an opaque Docker failure.
+    fn apply_resource_limits(command: &mut Command) -> Result<()> {
+        if let Ok(shm_size) = henv::var(SHM_SIZE_ENVVAR) {
+            try!(validate_size(&shm_size));
+            command.arg("--shm-size").arg(shm_size);
+        }
+        if let Ok(memory) = henv::var(MEMORY_ENVVAR) {
+            try!(validate_size(&memory));
+            // Setting `--memory-swap` to the same value as `--memory` disables additional swap,
+            // so the limit is a real ceiling rather than just throttling RAM use.
+            command.arg("--memory").arg(&memory).arg("--memory-swap").arg(&memory);
+        }
+        if let Ok(cpus) = henv::var(CPUS_ENVVAR) {
+            try!(validate_cpus(&cpus));
+            if is_cpuset(&cpus) {
+                command.arg("--cpuset-cpus").arg(cpus);
+            } else {
+                command.arg("--cpus").arg(cpus);
+            }
+        }
+        Ok(())
+    }
 
     pub fn start(_ui: &mut UI, args: Vec<OsString>) -> Result<()> {
         let docker = henv::var(DOCKER_CMD_ENVVAR).unwrap_or(DOCKER_CMD.to_string());
@@ -128,31 +351,55 @@ mod inner {
             None => return Err(Error::ExecCommandNotFound(docker.to_string())),
         };
 
-        let child = Command::new(&cmd)
-            .arg("pull")
-            .arg(&image_identifier())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .expect("docker failed to start");
+        let platform = studio_platform();
+        let image = image_identifier(platform_arch(&platform));
+        try!(ensure_trusted_image(&image));
+        let content_trust = henv::var(VERIFY_IMAGE_ENVVAR).is_ok();
+        let pull_policy = PullPolicy::from_env();
 
-        let output = child.wait_with_output()
-            .expect("failed to wait on child");
+        let should_pull = match pull_policy {
+            PullPolicy::Always => true,
+            PullPolicy::Missing => !image_exists_locally(&cmd, &image),
+            PullPolicy::Never => false,
+        };
 
-        if output.status.success() {
-            debug!("Docker image is reachable. Proceeding with launching docker.");
-        } else {
-            debug!("Docker image is unreachable. Exit code = {:?}",
-                   output.status);
+        if should_pull {
+            let mut pull = Command::new(&cmd);
+            pull.arg("pull").arg("--platform").arg(&platform).arg(&image);
+            if content_trust {
+                pull.env(DOCKER_CONTENT_TRUST_ENVVAR, "1");
+            }
+            let child = pull.stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .expect("docker failed to start");
 
-            let err_output = String::from_utf8(output.stderr).unwrap();
+            let output = child.wait_with_output()
+                .expect("failed to wait on child");
 
-            if err_output.contains("image") && err_output.contains("not found") {
-                return Err(Error::DockerImageNotFound(image_identifier().to_string()));
-            } else if err_output.contains("Cannot connect to the Docker daemon") {
-                return Err(Error::DockerDaemonDown);
+            if output.status.success() {
+                debug!("Docker image is reachable. Proceeding with launching docker.");
             } else {
-                return Err(Error::DockerNetworkDown(image_identifier().to_string()));
+                debug!("Docker image is unreachable. Exit code = {:?}",
+                       output.status);
+
+                let err_output = String::from_utf8(output.stderr).unwrap();
+
+                if err_output.contains("no matching manifest") ||
+                   err_output.contains("does not match the specified platform") {
+                    return Err(Error::DockerPlatformUnavailable(image, platform));
+                } else if err_output.contains("image") && err_output.contains("not found") {
+                    return Err(Error::DockerImageNotFound(image));
+                } else if err_output.contains("Cannot connect to the Docker daemon") {
+                    return Err(Error::DockerDaemonDown);
+                } else {
+                    return Err(Error::DockerNetworkDown(image));
+                }
+            }
+        } else {
+            debug!("Skipping `docker pull` for {} (pull policy is in effect)", image);
+            if pull_policy == PullPolicy::Never && !image_exists_locally(&cmd, &image) {
+                return Err(Error::DockerImageNotFound(image));
             }
         }
 
@@ -161,7 +408,13 @@ mod inner {
             .arg("--rm")
             .arg("--tty")
             .arg("--interactive")
-            .arg("--privileged");
+            .arg("--platform")
+            .arg(&platform);
+        try!(apply_security_profile(&mut command));
+        try!(apply_resource_limits(&mut command));
+        if content_trust {
+            command.env(DOCKER_CONTENT_TRUST_ENVVAR, "1");
+        }
 
         let env_vars = vec!["HAB_DEPOT_URL", "HAB_ORIGIN", "http_proxy", "https_proxy"];
         for var in env_vars {
@@ -182,7 +435,7 @@ mod inner {
                          CACHE_KEY_PATH))
             .arg("--volume")
             .arg(format!("{}:/src", env::current_dir().unwrap().to_string_lossy()))
-            .arg(image_identifier());
+            .arg(&image);
 
         for arg in &args {
             command.arg(arg);
@@ -205,20 +458,213 @@ mod inner {
     }
 
     /// Returns the Docker Studio image with tag for the desired version which corresponds to the
-    /// same version (minus release) as this program.
-    fn image_identifier() -> String {
+    /// same version (minus release) as this program. For anything other than `amd64` the tag is
+    /// suffixed with the Docker arch name (e.g. `:0.1.0-arm64`) so multi-arch image sets resolve
+    /// to the build that matches `arch`, unless an explicit image is given. `arch` should come
+    /// from `platform_arch(&studio_platform())` so an `HAB_STUDIO_PLATFORM` override (e.g. for
+    /// cross-arch/QEMU runs) resolves to a tag for the arch actually being run, not the host's.
+    fn image_identifier(arch: &str) -> String {
         let version: Vec<&str> = VERSION.split("/").collect();
-        henv::var(DOCKER_IMAGE_ENVVAR).unwrap_or(format!("{}:{}", DOCKER_IMAGE, version[0]))
+        henv::var(DOCKER_IMAGE_ENVVAR).unwrap_or_else(|_| if arch == "amd64" {
+            format!("{}:{}", DOCKER_IMAGE, version[0])
+        } else {
+            format!("{}:{}-{}", DOCKER_IMAGE, version[0], arch)
+        })
+    }
+
+    /// Translates Rust's `std::env::consts::ARCH` into Docker's platform architecture naming.
+    fn docker_arch() -> String {
+        match env::consts::ARCH {
+            "x86_64" => "amd64".to_string(),
+            "aarch64" => "arm64".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    /// The `--platform` value to pass to `docker pull`/`docker run`, defaulting to the host's own
+    /// architecture. Override with `HAB_STUDIO_PLATFORM` (e.g. `linux/arm64`) to run a Studio
+    /// built for a different architecture than the host, via the engine's emulation support.
+    fn studio_platform() -> String {
+        henv::var(PLATFORM_ENVVAR).unwrap_or(format!("linux/{}", docker_arch()))
+    }
+
+    /// Pulls the arch component (the part after the last `/`) out of a `--platform` value such
+    /// as `linux/arm64`, for resolving the image tag that matches it.
+    fn platform_arch(platform: &str) -> &str {
+        platform.rsplit('/').next().unwrap_or(platform)
+    }
+
+    /// Returns true if `image` is already present in the local Docker image cache. The `missing`
+    /// pull policy uses it to decide whether a pull is needed; `never` uses it to fail with
+    /// `DockerImageNotFound` before `docker run` when the image is absent.
+    fn image_exists_locally(docker_cmd: &PathBuf, image: &str) -> bool {
+        Command::new(docker_cmd)
+            .arg("image")
+            .arg("inspect")
+            .arg(image)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    /// The repo prefixes (registry host plus path, no tag) that a Studio image is allowed to
+    /// resolve to. Defaults to the official image; `HAB_STUDIO_TRUSTED_IMAGE_PREFIXES` extends
+    /// this with a `:`-separated list for self-hosted registries.
+    fn trusted_image_prefixes() -> Vec<String> {
+        let mut prefixes = vec![DOCKER_IMAGE.to_string()];
+        if let Ok(extra) = henv::var(TRUSTED_IMAGE_PREFIXES_ENVVAR) {
+            prefixes.extend(extra.split(':').map(|s| s.to_string()).filter(|s| !s.is_empty()));
+        }
+        prefixes
+    }
+
+    /// Splits a Docker image reference into its repo (registry host + path) and tag, without
+    /// mistaking a registry port's colon for the tag separator.
+    fn split_image_ref(image: &str) -> (String, Option<String>) {
+        match image.rfind(':') {
+            Some(idx) if !image[idx + 1..].contains('/') => {
+                (image[..idx].to_string(), Some(image[idx + 1..].to_string()))
+            }
+            _ => (image.to_string(), None),
+        }
+    }
+
+    /// Rejects an image reference whose repo doesn't match one of `trusted_image_prefixes()`.
+    /// This is what stands between a stale or poisoned `HAB_DOCKER_STUDIO_IMAGE` and a
+    /// container that runs with elevated capabilities (or `--privileged`) and mounts the Docker
+    /// socket.
+    ///
+    /// A match requires the repo to equal a trusted prefix exactly or to continue it at a `/`
+    /// boundary; a bare `starts_with` would let `.../studio-evil` pass just because it shares
+    /// `.../studio` as a string prefix, even though it's an entirely different repo.
+    fn ensure_trusted_image(image: &str) -> Result<()> {
+        let (repo, _) = split_image_ref(image);
+        let trusted = trusted_image_prefixes().iter().any(|prefix| {
+            repo == prefix.as_str() || repo.starts_with(&format!("{}/", prefix))
+        });
+        if trusted {
+            Ok(())
+        } else {
+            Err(Error::UntrustedDockerImage(image.to_string()))
+        }
     }
 
     #[cfg(test)]
     mod tests {
-        use super::{image_identifier, DOCKER_IMAGE};
+        use std::fs::File;
+        use std::io::Read;
+
+        use super::{docker_arch, ensure_trusted_image, image_identifier, is_cpuset,
+                    platform_arch, split_image_ref, studio_platform, validate_cpus,
+                    validate_size, write_default_seccomp_profile, PullPolicy, DOCKER_IMAGE};
         use VERSION;
 
         #[test]
         fn retrieve_image_identifier() {
-            assert_eq!(image_identifier(), format!("{}:{}", DOCKER_IMAGE, VERSION));
+            assert_eq!(image_identifier("amd64"),
+                       format!("{}:{}", DOCKER_IMAGE, VERSION));
+        }
+
+        #[test]
+        fn suffixes_the_tag_for_a_non_amd64_arch() {
+            assert_eq!(image_identifier("arm64"),
+                       format!("{}:{}-arm64", DOCKER_IMAGE, VERSION));
+        }
+
+        #[test]
+        fn image_identifier_follows_the_requested_arch_not_the_host() {
+            // HAB_STUDIO_PLATFORM lets a caller ask for an arch other than the host's own (e.g.
+            // to run under QEMU emulation); the resolved image tag must track that, not
+            // docker_arch().
+            let platform = "linux/arm64";
+            assert_eq!(image_identifier(platform_arch(platform)),
+                       format!("{}:{}-arm64", DOCKER_IMAGE, VERSION));
+        }
+
+        #[test]
+        fn extracts_arch_from_a_platform_string() {
+            assert_eq!(platform_arch("linux/arm64"), "arm64");
+            assert_eq!(platform_arch("linux/amd64"), "amd64");
+        }
+
+        #[test]
+        fn splits_registry_and_tag() {
+            assert_eq!(split_image_ref(&format!("{}:0.1.0", DOCKER_IMAGE)),
+                       (DOCKER_IMAGE.to_string(), Some("0.1.0".to_string())));
+            assert_eq!(split_image_ref("localhost:5000/studio:0.1.0"),
+                       ("localhost:5000/studio".to_string(), Some("0.1.0".to_string())));
+            assert_eq!(split_image_ref(DOCKER_IMAGE), (DOCKER_IMAGE.to_string(), None));
+        }
+
+        #[test]
+        fn trusts_the_default_image() {
+            assert!(ensure_trusted_image(&format!("{}:0.1.0", DOCKER_IMAGE)).is_ok());
+        }
+
+        #[test]
+        fn rejects_an_untrusted_image() {
+            assert!(ensure_trusted_image("evil-registry.example.com/studio:latest").is_err());
+        }
+
+        #[test]
+        fn rejects_a_repo_that_merely_shares_the_trusted_prefix() {
+            assert!(ensure_trusted_image(&format!("{}-evil:latest", DOCKER_IMAGE)).is_err());
+        }
+
+        #[test]
+        fn pull_policy_defaults_to_always() {
+            assert!(PullPolicy::from_env() == PullPolicy::Always);
+        }
+
+        #[test]
+        fn platform_defaults_to_host_arch() {
+            assert_eq!(studio_platform(), format!("linux/{}", docker_arch()));
+        }
+
+        #[test]
+        fn default_seccomp_profile_is_written_with_deny_default_and_clone3() {
+            let path = write_default_seccomp_profile().unwrap();
+            let mut contents = String::new();
+            File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+            assert!(contents.contains("SCMP_ACT_ERRNO"));
+            assert!(contents.contains("clone3"));
+        }
+
+        #[test]
+        fn validates_size_values() {
+            assert!(validate_size("512m").is_ok());
+            assert!(validate_size("2g").is_ok());
+            assert!(validate_size("1024").is_ok());
+            assert!(validate_size("bogus").is_err());
+            assert!(validate_size("2tb").is_err());
+        }
+
+        #[test]
+        fn validates_cpu_values() {
+            assert!(validate_cpus("1.5").is_ok());
+            assert!(validate_cpus("0-3").is_ok());
+            assert!(validate_cpus("0,2").is_ok());
+            assert!(validate_cpus("nope").is_err());
+        }
+
+        #[test]
+        fn rejects_negative_cpu_values() {
+            assert!(validate_cpus("-1").is_err());
+            assert!(validate_cpus("-1.5").is_err());
+        }
+
+        #[test]
+        fn distinguishes_cpuset_from_plain_count() {
+            assert!(!is_cpuset("1.5"));
+            assert!(is_cpuset("0-3"));
+            assert!(is_cpuset("0,2"));
+        }
+
+        #[test]
+        fn does_not_treat_a_negative_number_as_a_cpuset() {
+            assert!(!is_cpuset("-1"));
         }
     }
 }
@@ -0,0 +1,39 @@
+// Copyright (c) 2016 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{Arc, RwLock};
+
+use zmq;
+
+use supervisor::SupervisorConfig;
+
+/// A worker that a `Supervisor` spawns, monitors, and restarts on failure.
+///
+/// `Config` must implement `SupervisorConfig` so the `Supervisor` can read the heartbeat
+/// timeout and restart backoff settings for this worker type.
+pub trait Dispatcher: Sized + Send {
+    type Config: SupervisorConfig + Send + Sync;
+
+    /// Construct a new, not-yet-initialized worker sharing the application's config.
+    fn new(config: Arc<RwLock<Self::Config>>) -> Self;
+
+    /// One-time setup run before `start()`, on the worker's own thread.
+    fn init(&mut self) -> super::Result<()>;
+
+    /// Run the worker's main loop. `ctrl` is a `PAIR` socket connected back to the
+    /// `Supervisor`: send a readiness message on it as soon as `ctrl`/`work` are ready to use,
+    /// then a `b"HB"` heartbeat on a regular basis so the `Supervisor` doesn't declare this
+    /// worker dead. `work` is the `PULL` side of the dispatch bus that jobs arrive on.
+    fn start(&mut self, ctrl: zmq::Socket, work: zmq::Socket) -> super::Result<()>;
+}
@@ -13,84 +13,446 @@
 // limitations under the License.
 
 use std::marker::PhantomData;
-use std::sync::{mpsc, Arc, RwLock};
+use std::sync::{Arc, RwLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use zmq;
 
 use dispatcher::Dispatcher;
 
-pub struct Supervisor<T>
+const HEARTBEAT: &'static [u8] = b"HB";
+// How often (in milliseconds) the poll loop wakes up even without socket activity, so that
+// heartbeat timeouts and backoff delays are noticed promptly instead of only on the next message.
+const TICK_MS: i64 = 250;
+// How long `spawn_worker` waits for the new worker's readiness handshake before giving up. A
+// worker whose `init()` fails or which never connects must not be able to block this call
+// forever, since it runs on the supervisor's monitor thread.
+const READY_TIMEOUT_MS: i64 = 5_000;
+
+/// Per-worker config knobs for the liveness and crash-loop backoff policy. `Dispatcher::Config`
+/// must implement this so the supervisor can tune its behavior without hardcoding timings.
+pub trait SupervisorConfig {
+    /// Maximum time allowed between heartbeats before a worker is declared dead.
+    fn heartbeat_timeout(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Base delay for the exponential restart backoff: `base * 2^restart_count`.
+    fn restart_base_delay(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    /// Upper bound on the restart backoff delay, regardless of restart count.
+    fn restart_max_delay(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    /// Number of consecutive restarts allowed for a worker slot before the supervisor stops
+    /// trying and leaves the slot dead.
+    fn restart_max_retries(&self) -> u32 {
+        5
+    }
+
+    /// How long a worker must stay alive before its consecutive-restart counter resets to 0.
+    fn restart_reset_after(&self) -> Duration {
+        Duration::from_secs(300)
+    }
+}
+
+/// A worker's end of the dispatch bus, as seen by the `Supervisor`.
+///
+/// `ctrl` is a `PAIR` socket used for readiness, heartbeats, and detecting that the worker
+/// thread has torn down. `work` is the supervisor's `PUSH` half of the job queue; the worker
+/// connects a `PULL` socket to the other end. `handle` is the worker thread's `JoinHandle`, kept
+/// around so a heartbeat-declared death can be reaped instead of just abandoning the thread.
+struct WorkerChannel {
+    ctrl: zmq::Socket,
+    work: zmq::Socket,
+    handle: thread::JoinHandle<super::Result<()>>,
+    last_seen: Instant,
+    spawned_at: Instant,
+    restart_count: u32,
+}
+
+/// A worker slot that died and is waiting out its backoff delay before being re-spawned.
+struct PendingRestart {
+    worker_id: usize,
+    retry_at: Instant,
+    restart_count: u32,
+}
+
+// The supervisor's mutable state, shared between the handle(s) a caller holds and the monitor
+// thread `Supervisor::start` spawns. Lives behind a `RwLock` so `dispatch()` only needs a read
+// lock on the common case, while the monitor thread takes a write lock to reap dead workers and
+// spawn replacements.
+struct Inner<T>
     where T: Dispatcher
 {
     config: Arc<RwLock<T::Config>>,
-    workers: Vec<mpsc::Receiver<()>>,
+    ctx: zmq::Context,
+    workers: Vec<Option<WorkerChannel>>,
+    // Bumped every time a slot is (re)spawned, and folded into that spawn's inproc addresses.
+    // A worker slot declared dead by heartbeat timeout is freed up for a new worker immediately,
+    // without waiting for the old thread to actually exit; tagging addresses by generation means
+    // that old thread, if it's still alive due to a false-positive timeout, binds/connects to
+    // addresses nobody else uses instead of colliding with its replacement on the same queue.
+    generations: Vec<u64>,
+    pending: Vec<PendingRestart>,
     _marker: PhantomData<T>,
 }
 
+/// A handle to a worker pool. Cloning is cheap (it's a shared pointer to the same pool), so the
+/// value `start()` hands back can be kept around and used to `dispatch()` jobs while the monitor
+/// thread it spawned keeps watching heartbeats and backoff in the background.
+pub struct Supervisor<T>
+    where T: Dispatcher
+{
+    inner: Arc<RwLock<Inner<T>>>,
+}
+
+impl<T> Clone for Supervisor<T>
+    where T: Dispatcher
+{
+    fn clone(&self) -> Self {
+        Supervisor { inner: self.inner.clone() }
+    }
+}
+
 impl<T> Supervisor<T>
-    where T: Dispatcher + 'static
+    where T: Dispatcher + 'static,
+          T::Config: SupervisorConfig
 {
     // JW TODO: this should take a struct that implements "application config"
     pub fn new(config: Arc<RwLock<T::Config>>) -> Self {
         Supervisor {
-            config: config,
-            workers: vec![],
-            _marker: PhantomData,
+            inner: Arc::new(RwLock::new(Inner {
+                config: config,
+                ctx: zmq::Context::new(),
+                workers: vec![],
+                generations: vec![],
+                pending: vec![],
+                _marker: PhantomData,
+            })),
         }
     }
 
-    /// Start the supervisor and block until all workers are ready.
-    pub fn start(mut self, worker_count: usize) -> super::Result<()> {
-        try!(self.init(worker_count));
+    /// Start the worker pool, blocking until every worker is ready. Returns a handle that stays
+    /// usable for `dispatch()` after the monitor thread this spawns takes over heartbeat and
+    /// backoff bookkeeping in the background.
+    pub fn start(self, worker_count: usize) -> super::Result<Self> {
+        try!(self.inner.write().unwrap().init(worker_count));
         debug!("Supervisor ready");
-        self.run(worker_count)
+        self.run(worker_count);
+        Ok(self)
     }
 
-    // Initialize worker pool blocking until all workers are started and ready to begin processing
-    // requests.
-    fn init(&mut self, worker_count: usize) -> super::Result<()> {
-        for worker_id in 0..worker_count {
-            try!(self.spawn_worker(worker_id));
+    /// Push a job to a worker's work queue.
+    pub fn dispatch(&self, worker_id: usize, msg: &[u8]) -> super::Result<()> {
+        match self.inner.read().unwrap().workers[worker_id] {
+            Some(ref w) => {
+                try!(w.work.send(msg, 0));
+                Ok(())
+            }
+            None => Err(super::Error::WorkerNotRunning(worker_id)),
         }
-        Ok(())
     }
 
-    fn run(mut self, worker_count: usize) -> super::Result<()> {
+    // Block on a `zmq::poll()` across every live worker's control socket, waking at least every
+    // `TICK_MS` to check heartbeat timeouts and due backoff retries even when no socket is
+    // readable. Heartbeats refresh `last_seen`, and so does any other message (logged as
+    // unexpected); a worker is treated as dead when nothing arrives within `heartbeat_timeout()`
+    // or its control socket returns an error other than `EAGAIN`. Runs on a clone of this
+    // handle so the handle returned from `start()` keeps working for `dispatch()`.
+    fn run(&self, worker_count: usize) {
+        let supervisor = self.clone();
         thread::spawn(move || {
             loop {
+                let rc = {
+                    let inner = supervisor.inner.read().unwrap();
+                    let mut items: Vec<zmq::PollItem> = inner.workers
+                        .iter()
+                        .filter_map(|w| w.as_ref())
+                        .map(|w| w.ctrl.as_poll_item(zmq::POLLIN))
+                        .collect();
+                    zmq::poll(&mut items, TICK_MS)
+                };
+                if let Err(e) = rc {
+                    error!("Supervisor poll failed: {}", e);
+                }
+
+                let now = Instant::now();
+                let mut inner = supervisor.inner.write().unwrap();
+                let heartbeat_timeout = inner.config.read().unwrap().heartbeat_timeout();
+                let mut died = vec![];
                 for i in 0..worker_count {
-                    match self.workers[i].try_recv() {
-                        Err(mpsc::TryRecvError::Disconnected) => {
-                            info!("Worker[{}] restarting...", i);
-                            self.spawn_worker(i).unwrap();
+                    let timed_out = match inner.workers[i] {
+                        Some(ref mut w) => {
+                            match w.ctrl.recv_bytes(zmq::DONTWAIT) {
+                                Ok(ref msg) if msg.as_slice() == HEARTBEAT => {
+                                    w.last_seen = now;
+                                    false
+                                }
+                                Ok(msg) => {
+                                    warn!("Worker[{}] sent unexpected msg: {:?}", i, msg);
+                                    w.last_seen = now;
+                                    false
+                                }
+                                Err(zmq::Error::EAGAIN) => {
+                                    now.duration_since(w.last_seen) > heartbeat_timeout
+                                }
+                                Err(e) => {
+                                    warn!("Worker[{}] control channel error: {}", i, e);
+                                    true
+                                }
+                            }
                         }
-                        Ok(msg) => warn!("Worker[{}] sent unexpected msg: {:?}", i, msg),
-                        Err(mpsc::TryRecvError::Empty) => continue,
+                        None => false,
+                    };
+                    if timed_out {
+                        died.push((i, inner.workers[i].take().unwrap()));
+                    }
+                }
+                for (worker_id, dead) in died {
+                    warn!("Worker[{}] missed its heartbeat; scheduling restart", worker_id);
+                    // A worker that survived past the reset threshold before dying gets a clean
+                    // slate rather than inheriting its predecessor's restart count.
+                    let reset_after = inner.config.read().unwrap().restart_reset_after();
+                    let restart_count = if now.duration_since(dead.spawned_at) >= reset_after {
+                        0
+                    } else {
+                        dead.restart_count
+                    };
+                    // The timeout may be a false positive (GC pause, slow job, transient
+                    // overload), so the old thread isn't necessarily gone. Join it on a
+                    // dedicated reaper thread rather than the monitor thread, so a thread that
+                    // never exits doesn't re-introduce the hang this supervisor exists to avoid;
+                    // the freed slot's next spawn gets a new generation, so even a still-running
+                    // old thread can't collide with it on the same inproc addresses.
+                    thread::spawn(move || match dead.handle.join() {
+                        Ok(Ok(())) => debug!("Worker[{}] thread exited cleanly", worker_id),
+                        Ok(Err(e)) => warn!("Worker[{}] thread exited with error: {}", worker_id, e),
+                        Err(_) => warn!("Worker[{}] thread panicked", worker_id),
+                    });
+                    inner.schedule_restart(worker_id, restart_count);
+                }
+
+                let due: Vec<PendingRestart> = {
+                    let (due, pending): (Vec<_>, Vec<_>) = inner.pending
+                        .drain(..)
+                        .partition(|p| p.retry_at <= now);
+                    inner.pending = pending;
+                    due
+                };
+                for p in due {
+                    if let Err(e) = inner.spawn_worker(p.worker_id, p.restart_count) {
+                        error!("Worker[{}] restart attempt {} failed: {}",
+                               p.worker_id,
+                               p.restart_count,
+                               e);
+                        inner.schedule_restart(p.worker_id, p.restart_count);
                     }
                 }
-                // JW TODO: switching to zmq from channels will allow us to call select across
-                // multiple queues and avoid sleeping
-                thread::sleep(Duration::from_millis(500));
             }
         });
-        Ok(())
     }
+}
 
-    fn spawn_worker(&mut self, worker_id: usize) -> super::Result<()> {
+impl<T> Inner<T>
+    where T: Dispatcher + 'static,
+          T::Config: SupervisorConfig
+{
+    // Initialize worker pool blocking until all workers are started and ready to begin
+    // processing requests. A worker that never reaches "ready" is a failed start, not a
+    // degraded-but-ok one: callers rely on this returning `Ok` only when every slot is actually
+    // live, so report the slots that didn't come up instead of silently leaving them empty.
+    fn init(&mut self, worker_count: usize) -> super::Result<()> {
+        self.workers = (0..worker_count).map(|_| None).collect();
+        self.generations = (0..worker_count).map(|_| 0).collect();
+        let mut failed = vec![];
+        for worker_id in 0..worker_count {
+            if let Err(e) = self.spawn_worker(worker_id, 0) {
+                error!("Worker[{}] failed to start: {}", worker_id, e);
+                failed.push(worker_id);
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(super::Error::WorkersFailedToStart(failed))
+        }
+    }
+
+    // Queue a worker slot for re-spawn after an exponential backoff delay, unless it has already
+    // exhausted its retry budget.
+    fn schedule_restart(&mut self, worker_id: usize, restart_count: u32) {
+        let cfg = self.config.read().unwrap();
+        if restart_count >= cfg.restart_max_retries() {
+            error!("Worker[{}] exceeded {} consecutive restarts; giving up on this slot",
+                   worker_id,
+                   cfg.restart_max_retries());
+            return;
+        }
+        let delay = cfg.restart_base_delay()
+            .checked_mul(1u32 << restart_count)
+            .unwrap_or(cfg.restart_max_delay());
+        let delay = if delay > cfg.restart_max_delay() {
+            cfg.restart_max_delay()
+        } else {
+            delay
+        };
+        debug!("Worker[{}] restarting in {:?} (attempt {})",
+               worker_id,
+               delay,
+               restart_count + 1);
+        self.pending.push(PendingRestart {
+            worker_id: worker_id,
+            retry_at: Instant::now() + delay,
+            restart_count: restart_count + 1,
+        });
+    }
+
+    fn spawn_worker(&mut self, worker_id: usize, restart_count: u32) -> super::Result<()> {
         let cfg = self.config.clone();
-        let (tx, rx) = mpsc::sync_channel(1);
+        // Tag this spawn's addresses with a fresh generation so a previous occupant of this slot
+        // that's still alive (e.g. a heartbeat timeout that turned out to be a false positive)
+        // can never bind/connect onto the same queue as its replacement.
+        let generation = self.generations[worker_id];
+        self.generations[worker_id] += 1;
+        let ctrl_addr = format!("inproc://supervisor-ctrl-{}-{}", worker_id, generation);
+        let work_addr = format!("inproc://supervisor-work-{}-{}", worker_id, generation);
+
+        let ctrl = try!(self.ctx.socket(zmq::PAIR));
+        try!(ctrl.bind(&ctrl_addr));
+        let work = try!(self.ctx.socket(zmq::PUSH));
+        try!(work.bind(&work_addr));
+
+        let worker_ctx = self.ctx.clone();
         let mut worker = T::new(cfg);
-        thread::spawn(move || {
+        let handle = thread::spawn(move || -> super::Result<()> {
+            let worker_ctrl = try!(worker_ctx.socket(zmq::PAIR));
+            try!(worker_ctrl.connect(&ctrl_addr));
+            let worker_work = try!(worker_ctx.socket(zmq::PULL));
+            try!(worker_work.connect(&work_addr));
             try!(worker.init());
-            worker.start(tx)
+            worker.start(worker_ctrl, worker_work)
         });
-        if rx.recv().is_ok() {
-            debug!("Worker[{}] ready", worker_id);
-            self.workers.push(rx);
-        } else {
-            error!("Worker[{}] failed to start", worker_id);
-            self.workers.remove(worker_id);
+
+        // Wait for the worker to signal readiness over its control channel, but bound the wait:
+        // unlike the old `mpsc::Receiver`, a zmq `PAIR` socket gives no prompt disconnect signal
+        // if the peer never shows up, so an unbounded `recv` here could hang the monitor thread
+        // forever on a worker whose `init()` fails.
+        let mut ready_item = [ctrl.as_poll_item(zmq::POLLIN)];
+        let readable = match zmq::poll(&mut ready_item, READY_TIMEOUT_MS) {
+            Ok(n) => n > 0 && ready_item[0].is_readable(),
+            Err(e) => {
+                error!("Worker[{}] readiness poll failed: {}", worker_id, e);
+                false
+            }
+        };
+        if !readable {
+            error!("Worker[{}] did not become ready within {}ms",
+                   worker_id,
+                   READY_TIMEOUT_MS);
+            return Err(super::Error::WorkerSpawnTimeout(worker_id));
+        }
+
+        match ctrl.recv_bytes(zmq::DONTWAIT) {
+            Ok(_) => {
+                debug!("Worker[{}] ready", worker_id);
+                let now = Instant::now();
+                self.workers[worker_id] = Some(WorkerChannel {
+                    ctrl: ctrl,
+                    work: work,
+                    handle: handle,
+                    last_seen: now,
+                    spawned_at: now,
+                    restart_count: restart_count,
+                });
+                Ok(())
+            }
+            Err(e) => {
+                error!("Worker[{}] failed to start: {}", worker_id, e);
+                Err(super::Error::from(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::SupervisorConfig;
+
+    struct TestConfig {
+        base: Duration,
+        max: Duration,
+        max_retries: u32,
+    }
+
+    impl SupervisorConfig for TestConfig {
+        fn restart_base_delay(&self) -> Duration {
+            self.base
+        }
+
+        fn restart_max_delay(&self) -> Duration {
+            self.max
+        }
+
+        fn restart_max_retries(&self) -> u32 {
+            self.max_retries
         }
-        Ok(())
     }
-}
\ No newline at end of file
+
+    // Mirrors the `checked_mul`/capping math in `Inner::schedule_restart` without needing a real
+    // `Supervisor` (which requires a `Dispatcher` and live zmq sockets to construct).
+    fn backoff_delay(cfg: &TestConfig, restart_count: u32) -> Option<Duration> {
+        if restart_count >= cfg.restart_max_retries() {
+            return None;
+        }
+        let delay = cfg.restart_base_delay()
+            .checked_mul(1u32 << restart_count)
+            .unwrap_or(cfg.restart_max_delay());
+        Some(if delay > cfg.restart_max_delay() {
+            cfg.restart_max_delay()
+        } else {
+            delay
+        })
+    }
+
+    #[test]
+    fn backoff_delay_doubles_with_each_restart() {
+        let cfg = TestConfig {
+            base: Duration::from_millis(500),
+            max: Duration::from_secs(60),
+            max_retries: 10,
+        };
+        assert_eq!(backoff_delay(&cfg, 0), Some(Duration::from_millis(500)));
+        assert_eq!(backoff_delay(&cfg, 1), Some(Duration::from_millis(1_000)));
+        assert_eq!(backoff_delay(&cfg, 2), Some(Duration::from_millis(2_000)));
+        assert_eq!(backoff_delay(&cfg, 3), Some(Duration::from_millis(4_000)));
+    }
+
+    #[test]
+    fn backoff_delay_is_capped_at_restart_max_delay() {
+        let cfg = TestConfig {
+            base: Duration::from_millis(500),
+            max: Duration::from_secs(5),
+            max_retries: 20,
+        };
+        // 500ms * 2^10 would be far past the 5s cap.
+        assert_eq!(backoff_delay(&cfg, 10), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn backoff_delay_is_none_once_retries_are_exhausted() {
+        let cfg = TestConfig {
+            base: Duration::from_millis(500),
+            max: Duration::from_secs(60),
+            max_retries: 3,
+        };
+        assert!(backoff_delay(&cfg, 3).is_none());
+        assert!(backoff_delay(&cfg, 4).is_none());
+    }
+}